@@ -0,0 +1,119 @@
+//! Rustc-style diagnostics: a primary span with a caret label, any number of
+//! secondary labeled spans, and trailing notes, rendered against a shared
+//! source window (merging nearby spans onto one window instead of printing
+//! the same lines twice).
+
+use crate::source_map::LineIndex;
+use rowan::TextRange;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning
+}
+impl Severity {
+    fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning"
+        }
+    }
+}
+
+struct Label {
+    span: TextRange,
+    message: String,
+    primary: bool
+}
+
+pub struct Diagnostic {
+    severity: Severity,
+    message: String,
+    labels: Vec<Label>,
+    notes: Vec<String>
+}
+impl Diagnostic {
+    pub fn error(primary: TextRange, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, primary, message)
+    }
+    pub fn warning(primary: TextRange, message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, primary, message)
+    }
+    fn new(severity: Severity, primary: TextRange, message: impl Into<String>) -> Self {
+        let message = message.into();
+        Self {
+            severity,
+            labels: vec![Label { span: primary, message: message.clone(), primary: true }],
+            message,
+            notes: Vec::new()
+        }
+    }
+    pub fn with_label(mut self, span: TextRange, message: impl Into<String>) -> Self {
+        self.labels.push(Label { span, message: message.into(), primary: false });
+        self
+    }
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Renders this diagnostic to stderr against `code`, using `line_index`
+    /// for offset lookups.
+    pub fn print(&self, code: &str, line_index: &LineIndex) {
+        eprintln!("{}: {}", self.severity.as_str(), self.message);
+
+        let mut windows: Vec<(usize, usize)> = self.labels.iter()
+            .map(|label| line_to_range(line_index, label.span))
+            .collect();
+        windows.sort();
+        let mut merged: Vec<(usize, usize)> = Vec::new();
+        for (start, end) in windows.drain(..) {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 + 2 => last.1 = last.1.max(end),
+                _ => merged.push((start, end))
+            }
+        }
+
+        let llen = num_digits(merged.last().map(|&(_, e)| e + 1).unwrap_or(1));
+
+        for (first, last) in merged {
+            for line in first..=last {
+                let s = line_index.line_start(line);
+                let e = line_index.line_end(line);
+
+                eprintln!("{:>len$} {}", line + 1, &code[s..e], len = llen);
+
+                for label in &self.labels {
+                    let start = label.span.start().to_usize();
+                    let end = label.span.end().to_usize();
+                    if start < e && end > s {
+                        let col_start = start.saturating_sub(s);
+                        let col_end = (end - start).min(e - s - col_start).max(1);
+                        let marker = if label.primary { "^" } else { "-" };
+                        eprintln!("{:start$}{} {}", "", marker.repeat(col_end), label.message, start = llen + 1 + col_start);
+                    }
+                }
+            }
+        }
+
+        for note in &self.notes {
+            eprintln!("{:>len$} = note: {}", "", note, len = llen);
+        }
+    }
+}
+
+fn line_to_range(line_index: &LineIndex, span: TextRange) -> (usize, usize) {
+    let (start_line, _) = line_index.offset_to_line_col(span.start().to_usize());
+    let (end_line, end_col) = line_index.offset_to_line_col(span.end().to_usize());
+    let end_line = if end_col == 0 && end_line > start_line { end_line - 1 } else { end_line };
+    (start_line, end_line.max(start_line))
+}
+
+fn num_digits(mut n: usize) -> usize {
+    let mut len = 1;
+    while n >= 10 {
+        n /= 10;
+        len += 1;
+    }
+    len
+}
@@ -0,0 +1,32 @@
+//! A minimal, self-contained reporter for the parser's own `Spanned<ParseError>`
+//! diagnostics: one line of source context per error, a `^^^` underline
+//! beneath the offending span, and the message. Unlike `Diagnostic`, this
+//! returns the rendered text instead of printing it, so callers can decide
+//! where it goes.
+
+use crate::{
+    parser::{ParseError, Spanned},
+    source_map::LineIndex
+};
+use std::fmt::Write;
+
+/// Renders `errors` against `src`.
+pub fn render(src: &str, errors: &[Spanned<ParseError>]) -> String {
+    let line_index = LineIndex::new(src);
+    let mut out = String::new();
+
+    for error in errors {
+        let start = error.span.start().to_usize();
+        let end = error.span.end().to_usize();
+
+        let (line, col) = line_index.offset_to_line_col(start);
+        let s = line_index.line_start(line);
+        let e = line_index.line_end(line);
+
+        writeln!(out, "{}", &src[s..e]).unwrap();
+        writeln!(out, "{:col$}{}", "", "^".repeat((end - start).max(1).min(e - s)), col = col).unwrap();
+        writeln!(out, "error: {}", error.error).unwrap();
+    }
+
+    out
+}
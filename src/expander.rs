@@ -5,7 +5,7 @@ use crate::{
 };
 
 use rowan::TextRange;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Fail, PartialEq, Eq)]
 pub enum GenerateError {
@@ -16,7 +16,20 @@ pub enum GenerateError {
     #[fail(display = "there must only be exactly one start assignment")]
     MultipleStart,
     #[fail(display = "unknown variable or function {:?}", _0)]
-    Unknown(String)
+    Unknown(String),
+    /// Two `Function`s reduce to the same `(match_state, match_input)` but
+    /// disagree on what they do. Carries the span of the first definition
+    /// alongside this one (returned as the outer error span).
+    #[fail(display = "conflicting transitions: this rule disagrees with an earlier one reducing to the same state and input")]
+    ConflictingTransition(TextRange)
+}
+
+/// A reachable `(state, input)` pair with no matching transition: the
+/// machine would `Reject` if it ever got there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingTransition {
+    pub state: String,
+    pub input: Option<u8>
 }
 
 type Result<T> = std::result::Result<T, (Option<TextRange>, GenerateError)>;
@@ -42,15 +55,39 @@ pub struct Expanded {
     pub start: String,
     pub start_span: TextRange,
     pub functions: HashMap<FnSignature, Option<FnBody>>,
-    pub unreachable: Vec<TextRange>
+    pub unreachable: Vec<TextRange>,
+    pub warnings: Vec<MissingTransition>
 }
 
-#[derive(Default)]
-pub struct Expander {
-    functions: HashMap<FnSignature, Option<FnBody>>
+/// A duplicate definition for an already-resolved signature, whose
+/// `do_write`/`do_move` matched but whose `do_state` couldn't be checked
+/// on the spot: the signature it reduces to might still be mid-resolution
+/// further up the call stack (e.g. a state that self-loops or sits in a
+/// cycle), and resolving `body` right there to compare could recurse
+/// forever. Resolving it is deferred here until expansion has fully
+/// finished, at which point every signature is either resolved or
+/// unreachable, and the same resolution can't recurse.
+struct PendingConflict<R: rowan::TreeRoot<Types>> {
+    signature: FnSignature,
+    span: TextRange,
+    body: Node<R>,
+    vars: HashMap<String, String>
 }
-impl Expander {
-    pub fn expand<R: rowan::TreeRoot<Types>>(ast: Node<R>) -> Result<Expanded> {
+
+pub struct Expander<R: rowan::TreeRoot<Types>> {
+    functions: HashMap<FnSignature, Option<FnBody>>,
+    pending_conflicts: Vec<PendingConflict<R>>
+}
+impl<R: rowan::TreeRoot<Types>> Default for Expander<R> {
+    fn default() -> Self {
+        Self {
+            functions: HashMap::new(),
+            pending_conflicts: Vec::new()
+        }
+    }
+}
+impl<R: rowan::TreeRoot<Types>> Expander<R> {
+    pub fn expand(ast: Node<R>) -> Result<Expanded> {
         let root = Root::cast(ast).expect("invalid ast");
 
         // Find start
@@ -64,6 +101,30 @@ impl Expander {
         let mut expander = Self::default();
         let start_name = expander.expand_fn(&root, &start.target(), None)?;
 
+        // Every signature is now either resolved or never got past "in
+        // progress" (which would mean `expand_fn` above never returned), so
+        // resolving a deferred conflict's body here can't recurse back into
+        // something still unresolved. Resolving one can itself defer fresh
+        // conflicts (it hits other already-resolved signatures), so keep
+        // draining until a pass adds no more. Re-expanding a resolved state
+        // defers each of its rules again, so every (signature, rule) pair is
+        // checked only once; otherwise a cycle would keep re-deferring itself.
+        let mut checked = HashSet::new();
+        while !expander.pending_conflicts.is_empty() {
+            let pending = std::mem::take(&mut expander.pending_conflicts);
+            for conflict in pending {
+                if !checked.insert((conflict.signature.clone(), conflict.span.start().to_usize())) {
+                    continue;
+                }
+                let body = Type::cast(conflict.body).expect("invalid ast");
+                let next_name = expander.expand_fn(&root, &body, Some(&conflict.vars))?;
+                let existing = expander.functions.get(&conflict.signature).unwrap().as_ref().unwrap();
+                if existing.do_state != next_name {
+                    return Err((Some(conflict.span), GenerateError::ConflictingTransition(existing.span)));
+                }
+            }
+        }
+
         // Find paths not reachable from the start
         let mut unreachable = Vec::new();
         for f in root.functions() {
@@ -72,14 +133,35 @@ impl Expander {
             }
         }
 
+        // Find reachable (state, input) pairs with no matching transition
+        let reachable_states: HashSet<&str> = expander.functions.keys()
+            .map(|s| s.match_state.as_str())
+            .collect();
+        let seen_inputs: HashSet<Option<u8>> = expander.functions.keys()
+            .map(|s| s.match_input)
+            .collect();
+        let seen_signatures: HashSet<(&str, Option<u8>)> = expander.functions.keys()
+            .map(|s| (s.match_state.as_str(), s.match_input))
+            .collect();
+
+        let mut warnings = Vec::new();
+        for &state in &reachable_states {
+            for &input in &seen_inputs {
+                if !seen_signatures.contains(&(state, input)) {
+                    warnings.push(MissingTransition { state: state.to_string(), input });
+                }
+            }
+        }
+
         Ok(Expanded {
             start: start_name,
             start_span: start.node().range(),
             functions: expander.functions,
-            unreachable
+            unreachable,
+            warnings
         })
     }
-    fn expand_fn<R: rowan::TreeRoot<Types>>(
+    fn expand_fn(
         &mut self,
         root: &Root<R>,
         invocation: &Type<R>,
@@ -144,8 +226,31 @@ impl Expander {
                 original_len: name.len(),
                 match_input: f.match_input().value(),
             };
-            if self.functions.contains_key(&signature) {
-                return Ok(match_state.unwrap());
+            let do_write = f.do_write().value();
+            let do_move = f.do_move().operation();
+
+            match self.functions.get(&signature) {
+                // Already fully resolved: only a definite conflict if this
+                // definition disagrees with the one already recorded. Its
+                // `do_state` might *also* disagree, but checking that here
+                // would mean resolving `body`, which could still be mid
+                // resolution further up the call stack (self-loops, cycles)
+                // -- so that check is deferred, see `PendingConflict`.
+                Some(Some(existing)) => {
+                    if existing.do_write != do_write || existing.do_move != do_move {
+                        return Err((Some(f.node().range()), GenerateError::ConflictingTransition(existing.span)));
+                    }
+                    self.pending_conflicts.push(PendingConflict {
+                        signature,
+                        span: f.node().range(),
+                        body: body.node().clone(),
+                        vars: new_vars
+                    });
+                    continue;
+                },
+                // In progress further up the call stack: a legitimate cycle, not a conflict.
+                Some(None) => continue,
+                None => ()
             }
 
             // Mark in progress, don't re-evaluate (infinite loop)
@@ -155,9 +260,9 @@ impl Expander {
 
             self.functions.insert(signature, Some(FnBody {
                 span: f.node().range(),
-                do_write: f.do_write().value(),
+                do_write,
                 do_state: next_name,
-                do_move: f.do_move().operation()
+                do_move
             }));
         }
 
@@ -0,0 +1,107 @@
+//! A canonical pretty-printer for the lossless parse tree: single spaces
+//! around `=`, no space before `;`, `state char = char; nextstate move` laid
+//! out one definition per line. Trivia (the original whitespace/comment
+//! leaves the tree otherwise keeps around for diagnostics) is discarded in
+//! favour of this fixed layout.
+
+use crate::{
+    parser::{Node, Types},
+    tokenizer::Token,
+    types::{Char, Function, Movement, Root, SetStart, Type, TypedNode, Move}
+};
+use rowan::TextRange;
+
+/// Hook for injecting markup around specific node kinds while printing,
+/// e.g. a syntax highlighter wrapping a `Type`'s range in a span, or a doc
+/// generator linking a `Function`'s range to its definition. Modeled on
+/// rustc's `pprust::PpAnn`: downstream tools implement this instead of
+/// forking the printer.
+pub trait PpAnn {
+    fn pre(&mut self, _out: &mut String, _kind: Token, _range: TextRange) {}
+    fn post(&mut self, _out: &mut String, _kind: Token, _range: TextRange) {}
+}
+
+/// The default annotator: no markup, just the canonical text.
+pub struct NoAnn;
+impl PpAnn for NoAnn {}
+
+fn move_str(m: Move) -> &'static str {
+    match m {
+        Move::Current => "current",
+        Move::Next => "next",
+        Move::Prev => "prev"
+    }
+}
+
+fn print_type<R: rowan::TreeRoot<Types>>(ty: &Type<R>, out: &mut String, ann: &mut impl PpAnn) {
+    ann.pre(out, ty.node().kind(), ty.node().range());
+
+    out.push_str(ty.name().as_str());
+
+    let mut generics = ty.generics().peekable();
+    if generics.peek().is_some() {
+        out.push('<');
+        for (i, generic) in generics.enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            print_type(&generic, out, ann);
+        }
+        out.push('>');
+    }
+
+    ann.post(out, ty.node().kind(), ty.node().range());
+}
+fn print_char<R: rowan::TreeRoot<Types>>(c: &Char<R>, out: &mut String, ann: &mut impl PpAnn) {
+    ann.pre(out, c.node().kind(), c.node().range());
+    out.push_str(c.text().expect("invalid ast"));
+    ann.post(out, c.node().kind(), c.node().range());
+}
+fn print_movement<R: rowan::TreeRoot<Types>>(m: &Movement<R>, out: &mut String, ann: &mut impl PpAnn) {
+    ann.pre(out, m.node().kind(), m.node().range());
+    out.push_str(move_str(m.operation()));
+    ann.post(out, m.node().kind(), m.node().range());
+}
+fn print_set_start<R: rowan::TreeRoot<Types>>(s: &SetStart<R>, out: &mut String, ann: &mut impl PpAnn) {
+    ann.pre(out, s.node().kind(), s.node().range());
+    out.push_str("start = ");
+    print_type(&s.target(), out, ann);
+    ann.post(out, s.node().kind(), s.node().range());
+}
+fn print_function<R: rowan::TreeRoot<Types>>(f: &Function<R>, out: &mut String, ann: &mut impl PpAnn) {
+    ann.pre(out, f.node().kind(), f.node().range());
+
+    print_type(&f.match_state(), out, ann);
+    out.push(' ');
+    print_char(&f.match_input(), out, ann);
+    out.push_str(" = ");
+    print_char(&f.do_write(), out, ann);
+    out.push_str("; ");
+    print_type(&f.do_state(), out, ann);
+    out.push(' ');
+    print_movement(&f.do_move(), out, ann);
+
+    ann.post(out, f.node().kind(), f.node().range());
+}
+
+/// Reprints `ast` with canonical formatting, annotated via `ann`.
+pub fn format_with<R: rowan::TreeRoot<Types>>(ast: Node<R>, ann: &mut impl PpAnn) -> String {
+    let root = Root::cast(ast).expect("invalid ast");
+    let mut out = String::new();
+
+    for start in root.start_assignments() {
+        print_set_start(&start, &mut out, ann);
+        out.push('\n');
+    }
+    for function in root.functions() {
+        print_function(&function, &mut out, ann);
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Reprints `ast` with canonical formatting and no markup.
+pub fn format<R: rowan::TreeRoot<Types>>(ast: Node<R>) -> String {
+    format_with(ast, &mut NoAnn)
+}
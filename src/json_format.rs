@@ -0,0 +1,133 @@
+//! Import/export of a portable JSON transition-table format, so a flattened
+//! machine can be handed to or loaded from other Turing-machine tooling
+//! without going through this crate's own source syntax or `Expander`.
+
+use crate::{
+    expander::{Expanded, FnBody, FnSignature},
+    types::Move,
+    FINISH_STATE
+};
+use rowan::{TextRange, TextUnit};
+use serde_json::json;
+use std::collections::HashMap;
+
+#[derive(Debug, Fail, PartialEq, Eq)]
+pub enum JsonError {
+    #[fail(display = "malformed transition table: {}", _0)]
+    Malformed(String)
+}
+
+/// A zero-length span standing in for "no source location": transitions
+/// read back from JSON weren't parsed from this crate's syntax, so they
+/// have nothing real to point `Diagnostic`s at.
+fn no_span() -> TextRange {
+    TextRange::from_to(TextUnit::from(0), TextUnit::from(0))
+}
+
+fn move_str(m: Move) -> &'static str {
+    match m {
+        Move::Prev => "left",
+        Move::Current => "stay",
+        Move::Next => "right"
+    }
+}
+fn str_move(s: &str) -> Result<Move, JsonError> {
+    match s {
+        "left" => Ok(Move::Prev),
+        "stay" => Ok(Move::Current),
+        "right" => Ok(Move::Next),
+        other => Err(JsonError::Malformed(format!("unknown move {:?}, expected \"left\", \"stay\" or \"right\"", other)))
+    }
+}
+
+fn cell_to_json(c: Option<u8>) -> serde_json::Value {
+    match c {
+        Some(b) => (b as char).to_string().into(),
+        None => serde_json::Value::Null
+    }
+}
+fn cell_from_json(v: &serde_json::Value) -> Result<Option<u8>, JsonError> {
+    match v {
+        serde_json::Value::Null => Ok(None),
+        serde_json::Value::String(s) if s.chars().count() == 1 => Ok(Some(s.chars().next().unwrap() as u8)),
+        other => Err(JsonError::Malformed(format!("expected a single-character string or null, found {}", other)))
+    }
+}
+
+/// Serializes `expanded` into the portable format:
+/// `{ "start": <state>, "transitions": [{ "state", "read", "write", "move", "next" }, ...] }`.
+pub fn write(expanded: &Expanded) -> serde_json::Value {
+    let transitions: Vec<_> = expanded.functions.iter()
+        .map(|(key, body)| {
+            let body = body.as_ref().unwrap();
+            json!({
+                "state": key.match_state,
+                "read": cell_to_json(key.match_input),
+                "write": cell_to_json(body.do_write),
+                "move": move_str(body.do_move),
+                "next": body.do_state
+            })
+        })
+        .collect();
+
+    json!({
+        "start": expanded.start,
+        "transitions": transitions
+    })
+}
+
+/// Parses the portable format back into an `Expanded`, ready to hand
+/// straight to `Runner::new` without going through `Expander` at all.
+/// `unreachable`/`warnings` come back empty: that analysis walks the
+/// original generic source, which an already-flattened table doesn't have.
+pub fn read(json: &serde_json::Value) -> Result<Expanded, JsonError> {
+    let start = json.get("start")
+        .and_then(serde_json::Value::as_str)
+        .ok_or_else(|| JsonError::Malformed("missing \"start\"".to_string()))?
+        .to_string();
+
+    let transitions = json.get("transitions")
+        .and_then(serde_json::Value::as_array)
+        .ok_or_else(|| JsonError::Malformed("missing \"transitions\" array".to_string()))?;
+
+    let mut functions: HashMap<FnSignature, Option<FnBody>> = HashMap::new();
+    for transition in transitions {
+        let state = transition.get("state").and_then(serde_json::Value::as_str)
+            .ok_or_else(|| JsonError::Malformed("transition missing \"state\"".to_string()))?;
+        let next = transition.get("next").and_then(serde_json::Value::as_str)
+            .ok_or_else(|| JsonError::Malformed("transition missing \"next\"".to_string()))?;
+        let do_move = transition.get("move").and_then(serde_json::Value::as_str)
+            .ok_or_else(|| JsonError::Malformed("transition missing \"move\"".to_string()))
+            .and_then(str_move)?;
+        let read = transition.get("read")
+            .ok_or_else(|| JsonError::Malformed("transition missing \"read\"".to_string()))
+            .and_then(cell_from_json)?;
+        let write = transition.get("write")
+            .ok_or_else(|| JsonError::Malformed("transition missing \"write\"".to_string()))
+            .and_then(cell_from_json)?;
+
+        let signature = FnSignature {
+            match_state: state.to_string(),
+            original_len: state.len(),
+            match_input: read
+        };
+        functions.insert(signature, Some(FnBody {
+            span: no_span(),
+            do_write: write,
+            do_state: next.to_string(),
+            do_move
+        }));
+    }
+
+    if start != FINISH_STATE && !functions.keys().any(|s| s.match_state == start) {
+        return Err(JsonError::Malformed(format!("start state {:?} has no transitions", start)));
+    }
+
+    Ok(Expanded {
+        start,
+        start_span: no_span(),
+        functions,
+        unreachable: Vec::new(),
+        warnings: Vec::new()
+    })
+}
@@ -2,19 +2,26 @@
 #[macro_use] extern crate failure;
 
 use clap::{AppSettings, Arg, SubCommand};
-use rowan::TextRange;
 use std::{fs, io::{self, prelude::*}};
 
+mod diagnostics;
+mod err_reporting;
 mod expander;
+mod format;
+mod json_format;
 mod parser;
 mod runner;
+mod source_map;
 mod subcommands;
+mod token_set;
 mod tokenizer;
 mod types;
 
+use self::diagnostics::Diagnostic;
 use self::expander::Expander;
 use self::parser::{Node, Types, Parser};
-use self::tokenizer::{Token, Tokenizer};
+use self::source_map::LineIndex;
+use self::tokenizer::{LexError, Token, Tokenizer};
 
 pub const FINISH_STATE: &str = "finish";
 
@@ -23,10 +30,25 @@ fn main() -> io::Result<()> {
         .setting(AppSettings::SubcommandRequiredElseHelp)
         .arg(Arg::with_name("file")
             .help("Specifies the input file, defaults to STDIN"))
+        .arg(Arg::with_name("json")
+            .long("json")
+            .help("Treats the input as an already-flattened JSON transition table instead of source"))
         .subcommand(SubCommand::with_name("generate")
             .about("Generates input to https://turingmachinesimulator.com/"))
         .subcommand(SubCommand::with_name("interactive")
             .about("Runs the turing machine in an interactive debugger"))
+        .subcommand(SubCommand::with_name("codegen")
+            .about("Compiles the machine into a standalone Rust program"))
+        .subcommand(SubCommand::with_name("export-json")
+            .about("Prints the flattened machine as a portable JSON transition table"))
+        .subcommand(SubCommand::with_name("fmt")
+            .about("Reprints the input with canonical formatting"))
+        .subcommand(SubCommand::with_name("nondeterministic")
+            .about("Explores every non-deterministic branch, accepting as soon as any reaches the finish state")
+            .arg(Arg::with_name("max-depth")
+                .long("max-depth")
+                .takes_value(true)
+                .help("Bounds how deep the configuration search explores before giving up and rejecting")))
         .get_matches();
 
     let code = match matches.value_of("file") {
@@ -37,94 +59,126 @@ fn main() -> io::Result<()> {
         },
         Some(path) => fs::read_to_string(path)?
     };
-    let ast = Parser::new(Tokenizer::new(&code)).parse();
+
+    if matches.is_present("json") {
+        if matches.subcommand_name() == Some("fmt") {
+            eprintln!("error: fmt reprints source, it doesn't make sense with --json");
+            return Ok(());
+        }
+
+        let json = match serde_json::from_str(&code) {
+            Ok(json) => json,
+            Err(err) => {
+                eprintln!("error: invalid json: {}", err);
+                return Ok(());
+            }
+        };
+        let expanded = match json_format::read(&json) {
+            Ok(expanded) => expanded,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                return Ok(());
+            }
+        };
+        return dispatch(&matches, "", LineIndex::new(""), expanded);
+    }
+
+    let line_index = LineIndex::new(&code);
+
+    let mut tokenizer = Tokenizer::new(&code);
+    let tokens: Vec<_> = tokenizer.by_ref().collect();
+    let lex_errors = tokenizer.errors().to_vec();
+
+    let ast = Parser::new(tokens).parse();
     let ast = ast.borrowed();
 
-    let mut error = print_errors(&code, ast);
+    let mut error = print_errors(&code, &line_index, &mut lex_errors.into_iter(), ast);
     if !ast.root_data().is_empty() {
         error = true;
-        for error in ast.root_data() {
-            eprintln!("error: {}", error);
-        }
+        eprint!("{}", err_reporting::render(&code, ast.root_data()));
     }
     if error {
         return Ok(());
     }
 
+    if matches.subcommand_name() == Some("fmt") {
+        print!("{}", format::format(ast));
+        return Ok(());
+    }
+
     let expanded = match Expander::expand(ast) {
         Ok(functions) => functions,
         Err((span, err)) => {
-            if let Some(span) = span {
-                print_span(&code, span);
+            match (span, &err) {
+                (Some(span), expander::GenerateError::ConflictingTransition(earlier)) => {
+                    Diagnostic::error(span, format!("failed to expand: {}", err))
+                        .with_label(*earlier, "earlier conflicting definition here")
+                        .print(&code, &line_index)
+                },
+                (Some(span), _) => Diagnostic::error(span, format!("failed to expand: {}", err)).print(&code, &line_index),
+                (None, _) => eprintln!("error: failed to expand: {}", err)
             }
-            eprintln!("-> failed to expand: {}", err);
             return Ok(());
         }
     };
 
     for &span in &expanded.unreachable {
-        print_span(&code, span);
-        eprintln!("-> warning: unreachable code path");
+        Diagnostic::warning(span, "unreachable code path")
+            .with_note("this code is never reached from the start state")
+            .print(&code, &line_index);
+    }
+    for warning in &expanded.warnings {
+        match warning.input {
+            Some(input) => eprintln!("warning: state {:?} has no transition for input {:?}", warning.state, input as char),
+            None => eprintln!("warning: state {:?} has no transition for blank input", warning.state)
+        }
     }
 
+    dispatch(&matches, &code, line_index, expanded)
+}
+
+fn dispatch(matches: &clap::ArgMatches, code: &str, line_index: LineIndex, expanded: expander::Expanded) -> io::Result<()> {
     match matches.subcommand_name() {
         Some("generate") => subcommands::generate::main(expanded),
-        Some("interactive") => subcommands::interactive::interactive(&code, expanded),
+        Some("interactive") => subcommands::interactive::interactive(code, line_index, expanded),
+        Some("codegen") => subcommands::codegen::main(expanded),
+        Some("export-json") => println!("{}", serde_json::to_string_pretty(&json_format::write(&expanded)).unwrap()),
+        Some("nondeterministic") => {
+            let max_depth = matches.subcommand_matches("nondeterministic")
+                .and_then(|m| m.value_of("max-depth"))
+                .map(|s| s.parse().expect("--max-depth must be a number"));
+            subcommands::nondeterministic::main(expanded, max_depth);
+        },
         _ => unreachable!()
     }
 
     Ok(())
 }
-pub fn print_errors<R: rowan::TreeRoot<Types>>(code: &str, node: Node<R>) -> bool {
+/// Walks the tree reporting every `Token::Error` node through the shared
+/// `Diagnostic` renderer. Leaf error nodes came straight out of the
+/// tokenizer, so their specific cause is pulled off `lex_errors` (consumed
+/// in emission order); internal error nodes were built by the parser's
+/// recovery and only know their full offending span.
+pub fn print_errors<R: rowan::TreeRoot<Types>>(
+    code: &str,
+    line_index: &LineIndex,
+    lex_errors: &mut impl Iterator<Item = LexError>,
+    node: Node<R>
+) -> bool {
     let mut fail = false;
     if node.kind() == Token::Error {
-        print_span(code, node.range());
-        eprintln!("-> error: unexpected tokens");
-        fail = true;
+        if node.borrowed().leaf_text().is_some() {
+            if let Some(err) = lex_errors.next() {
+                Diagnostic::error(node.range(), err.message()).print(code, line_index);
+                fail = true;
+            }
+        } else {
+            Diagnostic::error(node.range(), "unexpected tokens").print(code, line_index);
+            fail = true;
+        }
     }
     for child in node.children() {
-        fail = print_errors(code, child) || fail;
+        fail = print_errors(code, line_index, lex_errors, child) || fail;
     }
     fail
 }
-pub fn print_span(code: &str, span: TextRange) {
-    let start = span.start().to_usize();
-    let end   = span.end().to_usize();
-
-    let mut ln = code[..end].lines().count();
-    let llen = {
-        let mut len = 1;
-        let mut ln = ln + code[end..].lines().skip(1).take(1).count();
-        while ln >= 10 {
-            ln /= 10;
-            len += 1;
-        }
-        len
-    };
-
-    let mut s = Some(start);
-    for _ in 0..2 {
-        s = s.and_then(|s| code[..s].rfind('\n'));
-        if s.is_some() {
-            ln -= 1;
-        }
-    }
-    let mut s = s.map(|i| i + 1).unwrap_or(0);
-
-    let mut prev = s;
-    while prev < end {
-        let next = s + code[s..].find('\n').map(|i| i + 1).unwrap_or(code.len() + 1 - s);
-
-        eprintln!("{:>len$} {}", ln, &code[s..next-1], len=llen);
-
-        if (start >= s && start < next) || (s >= start && end > s) {
-            let col_start = start.saturating_sub(s);
-            let col_end = (end - start).min(next-1 - s - col_start);
-            eprintln!("{:start$}{}", "", "^".repeat(col_end), start = llen + 1 + col_start);
-        }
-
-        prev = s;
-        s = next;
-        ln += 1;
-    }
-}
@@ -1,6 +1,7 @@
-use rowan::SmolStr;
-use std::iter::Peekable;
+use rowan::{GreenNodeBuilder, SmolStr, TextRange, TextUnit};
+use std::mem;
 use super::{
+    token_set::TokenSet,
     tokenizer::Token,
     FINISH_STATE
 };
@@ -21,59 +22,140 @@ pub enum ParseError {
     UnexpectedEof(Token)
 }
 
+/// A `ParseError` paired with the byte range of the token that triggered it,
+/// so a reporter can point at *where* in the source things went wrong.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Spanned<T> {
+    pub span: TextRange,
+    pub error: T
+}
+
 pub struct Types;
 impl rowan::Types for Types {
     type Kind = Token;
-    type RootData = Vec<ParseError>;
+    type RootData = Vec<Spanned<ParseError>>;
 }
 
 pub type Node<R = rowan::OwnedRoot<Types>> = rowan::SyntaxNode<Types, R>;
 
-pub struct Parser<I>
-    where I: Iterator<Item = (Token, SmolStr)>
-{
-    iter: Peekable<I>,
-    builder: rowan::GreenNodeBuilder<Types>,
-    errors: Vec<ParseError>
+/// One step of a parse, recorded instead of driving the `GreenNodeBuilder`
+/// directly. `process` replays these afterwards to build the actual tree.
+/// Recording first is what makes `forward_parent` possible below: a node's
+/// final `kind` can be fixed up after some of its children are already
+/// parsed, instead of requiring the parser to commit to a kind upfront.
+enum Event {
+    Start { kind: Token, forward_parent: Option<usize> },
+    Finish,
+    Token,
+    Error(Spanned<ParseError>)
+}
+
+/// An uncompleted node: the index of its `Event::Start` in the event stream.
+struct Marker(usize);
+/// A completed node, which can still be wrapped in a new parent via `precede`.
+struct CompletedMarker(usize);
+
+pub struct Parser {
+    tokens: Vec<(Token, SmolStr)>,
+    pos: usize,
+    offset: usize,
+    events: Vec<Event>
 }
-impl<I> Parser<I>
-    where I: Iterator<Item = (Token, SmolStr)>
-{
-    pub fn new<T>(iter: T) -> Self
-        where T: IntoIterator<Item = I::Item, IntoIter = I>
-    {
+impl Parser {
+    pub fn new<T: IntoIterator<Item = (Token, SmolStr)>>(iter: T) -> Self {
         Self {
-            iter: iter.into_iter().peekable(),
-            builder: rowan::GreenNodeBuilder::new(),
-            errors: Vec::new()
+            tokens: iter.into_iter().collect(),
+            pos: 0,
+            offset: 0,
+            events: Vec::new()
         }
     }
 
+    /// Opens a node of as-yet-unknown kind; fix it with `complete`.
+    fn start(&mut self) -> Marker {
+        let idx = self.events.len();
+        self.events.push(Event::Start { kind: Token::Tombstone, forward_parent: None });
+        Marker(idx)
+    }
+    /// Fixes `marker`'s kind and closes the node.
+    fn complete(&mut self, marker: Marker, kind: Token) -> CompletedMarker {
+        match &mut self.events[marker.0] {
+            Event::Start { kind: slot, .. } => *slot = kind,
+            _ => unreachable!()
+        }
+        self.events.push(Event::Finish);
+        CompletedMarker(marker.0)
+    }
+    /// Opens a new node that will end up wrapping the already-completed
+    /// `completed` node (and everything parsed since, once it's `complete`d
+    /// in turn) without needing to rewind and reparse.
+    fn precede(&mut self, completed: CompletedMarker) -> Marker {
+        let new_marker = self.start();
+        match &mut self.events[completed.0] {
+            Event::Start { forward_parent, .. } => *forward_parent = Some(new_marker.0),
+            _ => unreachable!()
+        }
+        new_marker
+    }
+
     fn peek_str(&mut self) -> Option<&(Token, SmolStr)> {
-        while self.iter.peek().map(|(t, _)| t.is_trivia()).unwrap_or(false) {
+        while self.tokens.get(self.pos).map(|(t, _)| t.is_trivia()).unwrap_or(false) {
             self.bump();
         }
-        self.iter.peek()
+        self.tokens.get(self.pos)
     }
     fn peek(&mut self) -> Option<Token> {
         self.peek_str().map(|&(t, _)| t)
     }
+    /// The byte range of the next non-trivia token, or a zero-length range
+    /// at the current offset if the input has run out.
+    fn current_span(&mut self) -> TextRange {
+        let offset = self.offset;
+        let len = self.peek_str().map(|(_, s)| s.len()).unwrap_or(0);
+        TextRange::from_to(TextUnit::from(offset as u32), TextUnit::from((offset + len) as u32))
+    }
+    fn push_error(&mut self, span: TextRange, error: ParseError) {
+        self.events.push(Event::Error(Spanned { span, error }));
+    }
     fn bump(&mut self) {
-        match self.iter.next() {
-            Some((token, s)) => self.builder.leaf(token, s),
-            None => self.errors.push(ParseError::UnexpectedEofGeneric)
+        match self.tokens.get(self.pos) {
+            Some((_, s)) => {
+                self.offset += s.len();
+                self.pos += 1;
+                self.events.push(Event::Token);
+            },
+            None => {
+                let span = self.current_span();
+                self.push_error(span, ParseError::UnexpectedEofGeneric);
+            }
         }
     }
-    fn expect(&mut self, expected: Token) {
+    /// Expects `expected`, recording an `Error` node over whatever bad tokens
+    /// precede it. Recovery never reads past `recovery` (always including
+    /// `Separator`, the statement boundary) or eof, so a malformed line can't
+    /// eat into the next one looking for a token that was never coming.
+    fn expect(&mut self, expected: Token, recovery: TokenSet) {
         match self.peek() {
-            None => self.errors.push(ParseError::UnexpectedEof(expected)),
+            None => {
+                let span = self.current_span();
+                self.push_error(span, ParseError::UnexpectedEof(expected));
+            },
+            Some(actual) if actual == expected => self.bump(),
             Some(actual) => {
-                if expected != actual {
-                    self.builder.start_internal(Token::Error);
-                    while { self.bump(); self.peek().map(|t| t != expected).unwrap_or(false) } {}
-                    self.builder.finish_internal();
+                let span = self.current_span();
+                let m = self.start();
+                let recovery = recovery.union(TokenSet::new(&[Token::Separator]));
+
+                while self.peek().map(|t| t != expected && !recovery.contains(t)).unwrap_or(false) {
+                    self.bump();
+                }
+
+                self.complete(m, Token::Error);
+                self.push_error(span, ParseError::Expected(expected, actual));
+
+                if self.peek() == Some(expected) {
+                    self.bump();
                 }
-                self.bump();
             }
         }
     }
@@ -82,75 +164,82 @@ impl<I> Parser<I>
             self.bump();
         }
     }
-    fn parse_ident(&mut self) {
-        self.builder.start_internal(Token::Type);
+    fn parse_ident(&mut self, recovery: TokenSet) {
+        let m = self.start();
 
-        self.expect(Token::Ident);
+        self.expect(Token::Ident, recovery.union(TokenSet::new(&[Token::AngleBOpen])));
 
         if self.peek() == Some(Token::AngleBOpen) {
             loop {
                 self.bump();
                 self.newlines();
-                self.parse_ident();
+                self.parse_ident(TokenSet::new(&[Token::Comma, Token::AngleBClose]));
 
                 if self.peek() != Some(Token::Comma) {
-                    self.expect(Token::AngleBClose);
+                    self.expect(Token::AngleBClose, recovery);
                     break;
                 }
             }
         }
 
-        self.builder.finish_internal();
+        self.complete(m, Token::Type);
     }
-    fn parse_next(&mut self) {
+    fn parse_next(&mut self) -> CompletedMarker {
         match self.peek_str() {
             Some((Token::Start, _)) => {
-                self.builder.start_internal(Token::SetStart);
+                let m = self.start();
 
                 self.bump();
-                self.expect(Token::Equal);
-                self.parse_ident();
+                self.expect(Token::Equal, TokenSet::new(&[Token::Ident]));
+                self.parse_ident(TokenSet::EMPTY);
 
-                self.builder.finish_internal();
+                self.complete(m, Token::SetStart)
             },
             Some((Token::Ident, ref s)) if s == FINISH_STATE => {
-                self.builder.start_internal(Token::Error);
+                let m = self.start();
                 self.bump();
-                self.builder.finish_internal();
+                self.complete(m, Token::Error)
             },
             Some((Token::Ident, _)) => {
-                self.builder.start_internal(Token::Function);
+                let m = self.start();
 
-                self.parse_ident();
-                self.expect(Token::Char);
-                self.expect(Token::Equal);
+                self.parse_ident(TokenSet::new(&[Token::Char]));
+                self.expect(Token::Char, TokenSet::new(&[Token::Equal]));
+                self.expect(Token::Equal, TokenSet::new(&[Token::Char]));
                 self.newlines();
-                self.expect(Token::Char);
-                self.expect(Token::Semicolon);
-                self.parse_ident();
+                self.expect(Token::Char, TokenSet::new(&[Token::Semicolon]));
+                self.expect(Token::Semicolon, TokenSet::new(&[Token::Ident]));
+                self.parse_ident(TokenSet::new(&[Token::Current, Token::Next, Token::Prev]));
 
                 if self.peek().map(|t| !t.is_move()).unwrap_or(false) {
-                    self.builder.start_internal(Token::Error);
-                    while { self.bump(); self.peek().map(|t| !t.is_move()).unwrap_or(false) } {}
-                    self.builder.finish_internal();
+                    let err_m = self.start();
+                    while self.peek().map(|t| !t.is_move() && t != Token::Separator).unwrap_or(false) {
+                        self.bump();
+                    }
+                    self.complete(err_m, Token::Error);
                 }
 
-                self.builder.start_internal(Token::Move);
+                let move_m = self.start();
                 self.bump();
-                self.builder.finish_internal();
+                self.complete(move_m, Token::Move);
 
-                self.builder.finish_internal();
+                self.complete(m, Token::Function)
+            },
+            None => {
+                let span = self.current_span();
+                self.push_error(span, ParseError::UnexpectedEofGeneric);
+                let m = self.start();
+                self.complete(m, Token::Error)
             },
-            None => self.errors.push(ParseError::UnexpectedEofGeneric),
             Some(_) => {
-                self.builder.start_internal(Token::Error);
+                let m = self.start();
                 self.bump();
-                self.builder.finish_internal();
+                self.complete(m, Token::Error)
             }
         }
     }
     pub fn parse(mut self) -> Node {
-        self.builder.start_internal(Token::Root);
+        let root = self.start();
 
         loop {
             self.newlines();
@@ -159,23 +248,77 @@ impl<I> Parser<I>
                 break;
             }
 
-            self.parse_next();
+            let stmt = self.parse_next();
 
             match self.peek() {
                 None | Some(Token::Separator) => (),
                 Some(_) => {
+                    // The statement just parsed turned out not to be the
+                    // whole line: wrap it (without rewinding or reparsing
+                    // it) in an outer Error node that also covers the
+                    // trailing garbage.
+                    let span = self.current_span();
+                    let wrap = self.precede(stmt);
                     self.bump();
-                    self.errors.push(ParseError::Trailing);
+                    while self.peek().map(|t| t != Token::Separator).unwrap_or(false) {
+                        self.bump();
+                    }
+                    self.push_error(span, ParseError::Trailing);
+                    self.complete(wrap, Token::Error);
                 }
             }
         }
 
-        self.builder.finish_internal();
+        self.complete(root, Token::Root);
 
-        Node::new(self.builder.finish(), self.errors)
+        process(self.tokens, self.events)
     }
 }
 
+/// Replays a recorded `Event` stream into an actual tree. The only subtlety
+/// is `forward_parent`: when a `Start` carries one, the node it names hasn't
+/// been opened yet either, so the whole chain is followed and opened
+/// outermost-first before any of their shared children are emitted, and each
+/// link is tombstoned so the main loop doesn't re-open it when it gets there.
+fn process(tokens: Vec<(Token, SmolStr)>, mut events: Vec<Event>) -> Node {
+    let mut builder: GreenNodeBuilder<Types> = GreenNodeBuilder::new();
+    let mut tokens = tokens.into_iter();
+    let mut errors = Vec::new();
+    let mut forward_parents = Vec::new();
+
+    for i in 0..events.len() {
+        let placeholder = Event::Start { kind: Token::Tombstone, forward_parent: None };
+        match mem::replace(&mut events[i], placeholder) {
+            Event::Start { kind: Token::Tombstone, forward_parent: None } => (),
+            Event::Start { kind, forward_parent } => {
+                forward_parents.push(kind);
+                let mut fp = forward_parent;
+                while let Some(idx) = fp {
+                    let placeholder = Event::Start { kind: Token::Tombstone, forward_parent: None };
+                    match mem::replace(&mut events[idx], placeholder) {
+                        Event::Start { kind, forward_parent } => {
+                            forward_parents.push(kind);
+                            fp = forward_parent;
+                        },
+                        _ => unreachable!()
+                    }
+                }
+                for kind in forward_parents.drain(..).rev() {
+                    builder.start_internal(kind);
+                }
+            },
+            Event::Finish => builder.finish_internal(),
+            Event::Token => {
+                let (kind, text) = tokens.next().expect("Event::Token without a matching token");
+                builder.leaf(kind, text);
+            },
+            Event::Error(err) => errors.push(err)
+        }
+    }
+
+    Node::new(builder.finish(), errors)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::tokenizer::Token;
@@ -191,7 +334,6 @@ mod tests {
         }
     }
     fn assert(tokens: Vec<(Token, SmolStr)>, expected: &str) {
-        let tokens = tokens.into_iter();
         let ast = Parser::new(tokens).parse();
 
         if !ast.root_data().is_empty() {
@@ -1,8 +1,9 @@
 use crate::{
     expander::{Expanded, FnBody},
-    parser::Move,
+    types::Move,
     FINISH_STATE
 };
+use std::collections::{HashSet, VecDeque};
 
 #[derive(Clone, Copy, Debug)]
 pub enum Status {
@@ -11,12 +12,25 @@ pub enum Status {
     Reject
 }
 
+/// Everything `step()` mutated, kept just long enough for `unstep()` to
+/// revert it: the cell value and position it wrote to, the state it moved
+/// away from, and whether that step allocated a new (always-`None`) tape
+/// cell that should simply be dropped again rather than restored.
+struct StepRecord {
+    prev_value: Option<u8>,
+    prev_i: isize,
+    prev_next_state: String,
+    grew_head: bool,
+    grew_tail: bool
+}
+
 pub struct Runner {
     pub expanded: Expanded,
     pub next_state: String,
     pub head: Vec<Option<u8>>,
     pub tail: Vec<Option<u8>>,
-    pub i: isize
+    pub i: isize,
+    history: Vec<StepRecord>
 }
 impl Runner {
     pub fn new(expanded: Expanded, mut input: Vec<Option<u8>>) -> Self {
@@ -29,7 +43,8 @@ impl Runner {
             next_state: start,
             head: Vec::new(),
             tail: input,
-            i: 0
+            i: 0,
+            history: Vec::new()
         }
     }
     pub fn buffer<'a>(&'a mut self) -> impl Iterator<Item = Option<u8>> + 'a {
@@ -67,24 +82,137 @@ impl Runner {
             None => return Status::Reject
         };
 
+        let prev_value = self.value();
+        let prev_i = self.i;
+        let prev_next_state = std::mem::replace(&mut self.next_state, f.do_state);
+
         *self.value_mut() = f.do_write;
-        self.next_state = f.do_state;
 
+        let mut grew_head = false;
+        let mut grew_tail = false;
         match f.do_move {
             Move::Current => (),
             Move::Next => {
                 self.i += 1;
                 if self.i >= self.tail.len() as isize {
                     self.tail.push(None);
+                    grew_tail = true;
                 }
             },
             Move::Prev => {
                 self.i -= 1;
                 if -self.i - 1 >= self.head.len() as isize {
                     self.head.push(None);
+                    grew_head = true;
                 }
             }
         }
+
+        self.history.push(StepRecord { prev_value, prev_i, prev_next_state, grew_head, grew_tail });
         Status::Progress
     }
+    /// Reverts the last `step()`, if any. Returns whether there was one to revert.
+    pub fn unstep(&mut self) -> bool {
+        let record = match self.history.pop() {
+            Some(record) => record,
+            None => return false
+        };
+
+        if record.grew_tail {
+            self.tail.pop();
+        }
+        if record.grew_head {
+            self.head.pop();
+        }
+
+        self.i = record.prev_i;
+        *self.value_mut() = record.prev_value;
+        self.next_state = record.prev_next_state;
+
+        true
+    }
+    /// Whether there is a recorded step that `unstep()` could revert.
+    pub fn can_unstep(&self) -> bool {
+        !self.history.is_empty()
+    }
+    /// Explores every non-deterministic branch as a BFS over machine
+    /// configurations `(next_state, head, tail, i)`, accepting as soon as
+    /// any branch reaches `FINISH_STATE`. Visited configurations are
+    /// hashed to prune revisits, and `max_depth` bounds the search so a
+    /// machine with no accepting run but an infinite branch still
+    /// terminates with `Status::Reject`.
+    pub fn run_nondeterministic(&self, max_depth: usize) -> Status {
+        let start = Configuration {
+            next_state: self.next_state.clone(),
+            head: self.head.clone(),
+            tail: self.tail.clone(),
+            i: self.i
+        };
+
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(start.clone());
+        queue.push_back((start, 0));
+
+        while let Some((config, depth)) = queue.pop_front() {
+            if config.next_state == FINISH_STATE {
+                return Status::Accept;
+            }
+            if depth >= max_depth {
+                continue;
+            }
+
+            let next_input = if config.i >= 0 {
+                config.tail[config.i as usize]
+            } else {
+                config.head[(-config.i as usize) - 1]
+            };
+
+            for (key, body) in self.expanded.functions.iter() {
+                if key.match_state != config.next_state || key.match_input != next_input {
+                    continue;
+                }
+                let body = body.as_ref().unwrap();
+                let mut next = config.clone();
+
+                if next.i >= 0 {
+                    next.tail[next.i as usize] = body.do_write;
+                } else {
+                    next.head[(-next.i as usize) - 1] = body.do_write;
+                }
+                next.next_state = body.do_state.clone();
+
+                match body.do_move {
+                    Move::Current => (),
+                    Move::Next => {
+                        next.i += 1;
+                        if next.i >= next.tail.len() as isize {
+                            next.tail.push(None);
+                        }
+                    },
+                    Move::Prev => {
+                        next.i -= 1;
+                        if -next.i - 1 >= next.head.len() as isize {
+                            next.head.push(None);
+                        }
+                    }
+                }
+
+                if visited.insert(next.clone()) {
+                    queue.push_back((next, depth + 1));
+                }
+            }
+        }
+
+        Status::Reject
+    }
+}
+
+/// A complete machine configuration, as searched over by `run_nondeterministic`.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Configuration {
+    next_state: String,
+    head: Vec<Option<u8>>,
+    tail: Vec<Option<u8>>,
+    i: isize
 }
@@ -0,0 +1,57 @@
+//! A small source map, in the spirit of proc-macro2's `SOURCE_MAP`/rustc's
+//! `CodeMap`: build the byte offset of every line start once, then answer
+//! offset<->line/column queries in O(log n) instead of rescanning the whole
+//! source on every diagnostic.
+
+/// Maps byte offsets into `(line, column)` pairs and back. Lines and columns
+/// are both 0-indexed; callers add 1 when displaying them.
+pub struct LineIndex {
+    /// Byte offset of the start of each line. `starts[0]` is always `0`.
+    starts: Vec<usize>,
+    len: usize
+}
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let mut starts = vec![0];
+        starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        Self { starts, len: text.len() }
+    }
+    /// The total number of lines in the source.
+    pub fn num_lines(&self) -> usize {
+        self.starts.len()
+    }
+    /// The `(line, col)` of a byte offset, both 0-indexed.
+    pub fn offset_to_line_col(&self, offset: usize) -> (usize, usize) {
+        let line = match self.starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1
+        };
+        (line, offset - self.starts[line])
+    }
+    /// The byte offset of the start of the given 0-indexed line.
+    pub fn line_start(&self, line: usize) -> usize {
+        self.starts[line]
+    }
+    /// The byte offset of the end of the given 0-indexed line, excluding its trailing newline.
+    pub fn line_end(&self, line: usize) -> usize {
+        self.starts.get(line + 1).map(|&s| s - 1).unwrap_or(self.len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic() {
+        let index = LineIndex::new("foo\nbar\nbaz");
+        assert_eq!(index.num_lines(), 3);
+        assert_eq!(index.offset_to_line_col(0), (0, 0));
+        assert_eq!(index.offset_to_line_col(3), (0, 3));
+        assert_eq!(index.offset_to_line_col(4), (1, 0));
+        assert_eq!(index.offset_to_line_col(9), (2, 1));
+        assert_eq!(index.line_start(1), 4);
+        assert_eq!(index.line_end(0), 3);
+        assert_eq!(index.line_end(2), 11);
+    }
+}
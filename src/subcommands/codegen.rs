@@ -0,0 +1,87 @@
+use crate::{
+    expander::Expanded,
+    types::Move,
+    FINISH_STATE
+};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Lowers `expanded` into a standalone `.rs` source that runs the machine
+/// directly, dispatching via `match (state, read)` over small interned
+/// state ids instead of paying `Runner::next_fn`'s per-step `String`
+/// comparisons and `HashMap` lookups. The output doesn't depend on this
+/// crate at all.
+pub fn main(expanded: Expanded) {
+    let mut ids: HashMap<String, usize> = HashMap::new();
+    let mut intern = |name: &str| -> usize {
+        let next_id = ids.len();
+        *ids.entry(name.to_string()).or_insert(next_id)
+    };
+
+    let finish_id = intern(FINISH_STATE);
+    let start_id = intern(&expanded.start);
+
+    let mut transitions = String::new();
+    for (key, body) in &expanded.functions {
+        let body = body.as_ref().unwrap();
+        let state_id = intern(&key.match_state);
+        let next_id = intern(&body.do_state);
+        let delta = match body.do_move {
+            Move::Current => 0,
+            Move::Next => 1,
+            Move::Prev => -1
+        };
+        writeln!(
+            transitions,
+            "            ({}, {}) => {{ *cell = {}; state = {}; i += {}; }},",
+            state_id, cell_literal(key.match_input), cell_literal(body.do_write), next_id, delta
+        ).unwrap();
+    }
+
+    print!(
+        r#"// Generated by `generic-turing-machine codegen`. Do not edit by hand.
+fn main() {{
+    let mut input = String::new();
+    std::io::Read::read_to_string(&mut std::io::stdin(), &mut input).unwrap();
+    let input = input.trim_end_matches('\n');
+    let mut tape: Vec<Option<u8>> = input.bytes().map(|c| Some(c).filter(|&c| c != b' ')).collect();
+    if tape.is_empty() {{
+        tape.push(None);
+    }}
+
+    let mut state: usize = {start_id};
+    let mut i: isize = 0;
+
+    loop {{
+        if state == {finish_id} {{
+            println!("accept");
+            return;
+        }}
+        while i < 0 {{
+            tape.insert(0, None);
+            i += 1;
+        }}
+        while i as usize >= tape.len() {{
+            tape.push(None);
+        }}
+        let cell = &mut tape[i as usize];
+        let read = *cell;
+
+        match (state, read) {{
+{transitions}            _ => {{ println!("reject"); return; }}
+        }}
+    }}
+}}
+"#,
+        start_id = start_id,
+        finish_id = finish_id,
+        transitions = transitions
+    );
+}
+
+fn cell_literal(c: Option<u8>) -> String {
+    match c {
+        Some(b) => format!("Some({})", b),
+        None => "None".to_string()
+    }
+}
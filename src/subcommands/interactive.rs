@@ -1,30 +1,55 @@
 use crate::{
     expander::Expanded,
-    runner::{Runner, Status}
+    runner::{Runner, Status},
+    source_map::LineIndex
 };
 use rustyline::{error::ReadlineError, Editor};
 use std::collections::HashMap;
 
-pub fn interactive(code: &str, expanded: Expanded) {
-    if let Some(mut app) = Interactive::new(code, expanded) {
+pub fn interactive(code: &str, line_index: LineIndex, expanded: Expanded) {
+    if let Some(mut app) = Interactive::new(code, line_index, expanded) {
         app.main();
     }
 }
 
+/// Renders a tape cell for the interactive tape display. Blank cells show as
+/// a space and printable ASCII shows as itself; everything else (control
+/// characters decoded from `\n`/`\t`/`\xHH` escapes, high bytes, ...) would
+/// otherwise break the tape's one-column-per-cell alignment, so it's shown
+/// as a placeholder dot instead.
+fn render_cell(c: Option<u8>) -> char {
+    match c {
+        None => ' ',
+        Some(b) if b.is_ascii_graphic() || b == b' ' => b as char,
+        Some(_) => '.'
+    }
+}
+
+/// Where to stop the machine: either when it's about to run a function
+/// defined on a given source line (`Line`), or when the read/write head
+/// reaches a given tape position (`Position`).
+enum Breakpoint {
+    Line(u32),
+    Position(isize)
+}
+
 struct Interactive<'a> {
-    breakpoints: HashMap<usize, u32>,
+    breakpoints: HashMap<usize, Breakpoint>,
     breakpoint_id: usize,
     code: &'a str,
+    line_index: LineIndex,
     editor: Editor<()>,
     runner: Runner
 }
 impl<'a> Interactive<'a> {
-    pub fn new(code: &'a str, expanded: Expanded) -> Option<Self> {
+    pub fn new(code: &'a str, line_index: LineIndex, expanded: Expanded) -> Option<Self> {
         println!("Welcome to an interactive turing machine runner!");
-        println!("To set a breakpoint, type `breakpoint` and the line number.");
+        println!("To set a breakpoint on a source line, type `breakpoint` and the line number.");
+        println!("To set a breakpoint on a tape position, type `tapebreak` and the position.");
         println!("To clear breakpoints, type `clear` and optionally specify a number.");
         println!("To run the machine until the next breakpoint, type `run`.");
         println!("To step the machine, type `step` or `next`.");
+        println!("To step the machine backwards, type `back`.");
 
         let mut editor = Editor::<()>::new();
         println!();
@@ -43,6 +68,7 @@ impl<'a> Interactive<'a> {
             breakpoints: HashMap::new(),
             breakpoint_id: 1,
             code,
+            line_index,
             editor,
             runner: Runner::new(expanded, input)
         })
@@ -55,6 +81,11 @@ impl<'a> Interactive<'a> {
             Status::Reject => { eprintln!("rejected: no matching state handler"); true }
         }
     }
+    fn unstep(&mut self) {
+        if !self.runner.unstep() {
+            eprintln!("already at the first step");
+        }
+    }
     fn print_location(&mut self) {
         if let Some(f) = self.runner.next_fn() {
             println!("Next state: {}", self.runner.next_state);
@@ -63,8 +94,8 @@ impl<'a> Interactive<'a> {
                 .unwrap_or_else(|| self.code[start..]
                     .find('\n')
                     .unwrap_or(self.code.len() - start));
-            let line = 1 + &self.code[..start].lines().count();
-            println!("{} {}", line, &self.code[start..end]);
+            let (line, _) = self.line_index.offset_to_line_col(start);
+            println!("{} {}", line + 1, &self.code[start..end]);
         }
 
         let i = (self.runner.head.len() as isize + self.runner.i) as usize;
@@ -75,7 +106,7 @@ impl<'a> Interactive<'a> {
         if iter.peek().is_some() {
             print!("Tape: {:leading$}", "", leading = 5usize.saturating_sub(i));
             for c in iter {
-                print!("{}", c.unwrap_or(b' ') as char);
+                print!("{}", render_cell(c));
             }
             println!();
             println!("           ^");
@@ -88,7 +119,7 @@ impl<'a> Interactive<'a> {
 
         let mut last = None;
 
-        'main: loop {
+        loop {
             let line = match self.editor.readline("> ") {
                 Ok(ref line) if line.is_empty() => last.unwrap_or_else(String::new),
                 Ok(line) => {
@@ -111,24 +142,31 @@ impl<'a> Interactive<'a> {
 
             match &*cmd {
                 "breakpoint" | "b" => {
-                    let line = match args.next().and_then(|arg| arg.parse().ok()) {
+                    let line: usize = match args.next().and_then(|arg| arg.parse().ok()) {
                         Some(line) => line,
                         None => {
                             eprintln!("breakpoint <line>");
                             continue;
                         }
                     };
-                    let mut offset = 0;
-                    for _ in 1..line {
-                        offset += 1 + match self.code[offset..].find('\n') {
-                            Some(br) => br,
-                            None => {
-                                eprintln!("invalid line");
-                                continue 'main;
-                            }
-                        };
+                    if line == 0 || line > self.line_index.num_lines() {
+                        eprintln!("invalid line");
+                        continue;
                     }
-                    self.breakpoints.insert(self.breakpoint_id, offset as u32);
+                    let offset = self.line_index.line_start(line - 1);
+                    self.breakpoints.insert(self.breakpoint_id, Breakpoint::Line(offset as u32));
+                    println!("Breakpoint #{} created!", self.breakpoint_id);
+                    self.breakpoint_id += 1;
+                },
+                "tapebreak" | "tb" => {
+                    let pos: isize = match args.next().and_then(|arg| arg.parse().ok()) {
+                        Some(pos) => pos,
+                        None => {
+                            eprintln!("tapebreak <position>");
+                            continue;
+                        }
+                    };
+                    self.breakpoints.insert(self.breakpoint_id, Breakpoint::Position(pos));
                     println!("Breakpoint #{} created!", self.breakpoint_id);
                     self.breakpoint_id += 1;
                 },
@@ -157,8 +195,12 @@ impl<'a> Interactive<'a> {
                             break;
                         }
                         if let Some(f) = self.runner.next_fn() {
-                            for (i, &b) in &self.breakpoints {
-                                if b >= f.span.start && f.span.end.map(|end| b < end).unwrap_or(false) {
+                            for (i, b) in &self.breakpoints {
+                                let hit = match *b {
+                                    Breakpoint::Line(b) => b >= f.span.start && f.span.end.map(|end| b < end).unwrap_or(false),
+                                    Breakpoint::Position(pos) => pos == self.runner.i
+                                };
+                                if hit {
                                     println!("Breakpoint #{} reached", i);
                                     break 'run;
                                 }
@@ -171,6 +213,10 @@ impl<'a> Interactive<'a> {
                     self.step();
                     self.print_location();
                 },
+                "back" | "unstep" | "u" => {
+                    self.unstep();
+                    self.print_location();
+                },
                 _ => eprintln!("unknown command")
             }
         }
@@ -0,0 +1,23 @@
+use crate::{
+    expander::Expanded,
+    runner::{Runner, Status}
+};
+use std::io::{self, prelude::*};
+
+/// Bounds `run_nondeterministic`'s configuration search; a machine with no
+/// accepting run but an infinite branch would otherwise never return.
+const DEFAULT_MAX_DEPTH: usize = 10_000;
+
+pub fn main(expanded: Expanded, max_depth: Option<usize>) {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input).unwrap();
+    let input = input.trim_end_matches('\n');
+    let tape = input.bytes().map(|c| Some(c).filter(|&c| c != b' ')).collect();
+
+    let runner = Runner::new(expanded, tape);
+    match runner.run_nondeterministic(max_depth.unwrap_or(DEFAULT_MAX_DEPTH)) {
+        Status::Accept => println!("accept"),
+        Status::Reject => println!("reject"),
+        Status::Progress => unreachable!()
+    }
+}
@@ -0,0 +1,24 @@
+//! A small bitset over `Token` discriminants, used to bound parser error
+//! recovery to a caller-chosen follow set instead of always resyncing on one
+//! single expected token.
+
+use crate::tokenizer::Token;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TokenSet(u128);
+impl TokenSet {
+    pub const EMPTY: TokenSet = TokenSet(0);
+
+    pub fn new(tokens: &[Token]) -> TokenSet {
+        tokens.iter().fold(TokenSet::EMPTY, |set, &t| set.insert(t))
+    }
+    pub fn insert(self, token: Token) -> TokenSet {
+        TokenSet(self.0 | (1 << token as u32))
+    }
+    pub fn union(self, other: TokenSet) -> TokenSet {
+        TokenSet(self.0 | other.0)
+    }
+    pub fn contains(self, token: Token) -> bool {
+        self.0 & (1 << token as u32) != 0
+    }
+}
@@ -1,5 +1,43 @@
 use rowan::SmolStr;
 
+/// A structured lexical error, carrying the byte offset it occurred at so
+/// callers can point directly at the offending source instead of just the
+/// opaque `Token::Error` slice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LexError {
+    Unexpected(usize, char),
+    UnterminatedBlockComment(usize),
+    BadCharLiteral(usize),
+    NonAsciiChar(usize),
+    InvalidEscape(usize, char),
+    InvalidHexEscape(usize),
+    InvalidEscapeValue(usize)
+}
+impl LexError {
+    pub fn offset(&self) -> usize {
+        match *self {
+            LexError::Unexpected(pos, _) => pos,
+            LexError::UnterminatedBlockComment(pos) => pos,
+            LexError::BadCharLiteral(pos) => pos,
+            LexError::NonAsciiChar(pos) => pos,
+            LexError::InvalidEscape(pos, _) => pos,
+            LexError::InvalidHexEscape(pos) => pos,
+            LexError::InvalidEscapeValue(pos) => pos
+        }
+    }
+    pub fn message(&self) -> String {
+        match *self {
+            LexError::Unexpected(_, c) => format!("unexpected character {:?}", c),
+            LexError::UnterminatedBlockComment(_) => "unterminated block comment".to_string(),
+            LexError::BadCharLiteral(_) => "char literal must contain exactly one (possibly escaped) byte".to_string(),
+            LexError::NonAsciiChar(_) => "char literal must be ASCII".to_string(),
+            LexError::InvalidEscape(_, c) => format!("invalid escape sequence \\{}", c),
+            LexError::InvalidHexEscape(_) => "invalid hex escape, expected \\xHH with two hex digits".to_string(),
+            LexError::InvalidEscapeValue(_) => "escape value out of range for a byte (must be <= 0xFF)".to_string()
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Token {
     // Meta
@@ -12,6 +50,9 @@ pub enum Token {
     Move,
     Root,
     SetStart,
+    /// Never produced by the tokenizer: a placeholder `Parser::start()` uses
+    /// for a node whose real kind isn't decided yet (or is abandoned).
+    Tombstone,
     Type,
 
     // Characters
@@ -47,18 +88,23 @@ impl Token {
     }
 }
 
-#[derive(Clone, Copy)]
 pub struct Tokenizer<'a> {
     input: &'a str,
-    offset: usize
+    offset: usize,
+    errors: Vec<LexError>
 }
 impl<'a> Tokenizer<'a> {
     pub fn new(input: &'a str) -> Self {
         Self {
             input,
-            offset: 0
+            offset: 0,
+            errors: Vec::new()
         }
     }
+    /// Structured lexical errors collected so far, in the order they were encountered.
+    pub fn errors(&self) -> &[LexError] {
+        &self.errors
+    }
     fn peek(&self) -> Option<char> {
         self.input[self.offset..].chars().next()
     }
@@ -72,15 +118,15 @@ impl<'a> Iterator for Tokenizer<'a> {
     type Item = (Token, SmolStr);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut start = *self;
+        let mut start_offset = self.offset;
         while self.peek().map(|c| c.is_whitespace() && c != '\n').unwrap_or(false) {
             self.next().unwrap();
         }
-        if self.offset > start.offset {
-            let s = SmolStr::new(&start.input[start.offset..self.offset]);
+        if self.offset > start_offset {
+            let s = SmolStr::new(&self.input[start_offset..self.offset]);
             return Some((Token::Whitespace, s))
         }
-        start = *self;
+        start_offset = self.offset;
         let c = self.next()?;
 
         match c {
@@ -103,29 +149,99 @@ impl<'a> Iterator for Tokenizer<'a> {
                         }
                     }
                 }
+                if !ended {
+                    self.errors.push(LexError::UnterminatedBlockComment(start_offset));
+                }
                 Some((
                     if ended { Token::Comment } else { Token::Error },
-                    SmolStr::new(&start.input[start.offset..self.offset])
+                    SmolStr::new(&self.input[start_offset..self.offset])
                 ))
             },
             '/' if self.peek() == Some('/') => {
                 self.next().unwrap();
 
                 while self.next().map(|c| c != '\n').unwrap_or(false) {}
-                Some((Token::Comment, SmolStr::new(&start.input[start.offset..self.offset])))
+                Some((Token::Comment, SmolStr::new(&self.input[start_offset..self.offset])))
             },
             '\'' => {
                 let c = self.next()?;
+                let mut ok = true;
 
-                if self.next()? != '\'' {
-                    Some((Token::Error, SmolStr::new(&start.input[start.offset..self.offset])))
+                if c == '\\' {
+                    match self.next() {
+                        Some('n') | Some('t') | Some('r') | Some('0') | Some('\\') | Some('\'') => (),
+                        Some('x') => {
+                            for _ in 0..2 {
+                                if self.peek().map(|c| c.is_ascii_hexdigit()).unwrap_or(false) {
+                                    self.next().unwrap();
+                                } else {
+                                    self.errors.push(LexError::InvalidHexEscape(start_offset));
+                                    ok = false;
+                                    break;
+                                }
+                            }
+                        },
+                        Some('u') => {
+                            if self.peek() == Some('{') {
+                                self.next().unwrap();
+                                let mut value: u32 = 0;
+                                loop {
+                                    match self.next() {
+                                        Some('}') => break,
+                                        Some(c) => match c.to_digit(16) {
+                                            Some(digit) => {
+                                                value = value * 16 + digit;
+                                                if value > 0xFF {
+                                                    self.errors.push(LexError::InvalidEscapeValue(start_offset));
+                                                    ok = false;
+                                                    break;
+                                                }
+                                            },
+                                            None => {
+                                                self.errors.push(LexError::InvalidHexEscape(start_offset));
+                                                ok = false;
+                                                break;
+                                            }
+                                        },
+                                        None => {
+                                            self.errors.push(LexError::InvalidHexEscape(start_offset));
+                                            ok = false;
+                                            break;
+                                        }
+                                    }
+                                }
+                            } else {
+                                self.errors.push(LexError::InvalidHexEscape(start_offset));
+                                ok = false;
+                            }
+                        },
+                        Some(other) => {
+                            self.errors.push(LexError::InvalidEscape(start_offset, other));
+                            ok = false;
+                        },
+                        None => {
+                            self.errors.push(LexError::BadCharLiteral(start_offset));
+                            ok = false;
+                        }
+                    }
                 } else if !c.is_ascii() {
-                    Some((Token::Error, SmolStr::new(&start.input[start.offset..self.offset])))
-                } else {
-                    Some((Token::Char, SmolStr::new(&start.input[start.offset..self.offset])))
+                    self.errors.push(LexError::NonAsciiChar(start_offset));
+                    ok = false;
                 }
+
+                if self.next() != Some('\'') {
+                    if ok {
+                        self.errors.push(LexError::BadCharLiteral(start_offset));
+                    }
+                    ok = false;
+                }
+
+                Some((
+                    if ok { Token::Char } else { Token::Error },
+                    SmolStr::new(&self.input[start_offset..self.offset])
+                ))
             },
-            '0'..='9' => Some((Token::Char, SmolStr::new(&start.input[start.offset..self.offset]))),
+            '0'..='9' => Some((Token::Char, SmolStr::new(&self.input[start_offset..self.offset]))),
             'a'..='z' | 'A'..='Z' => {
                 loop {
                     match self.peek() {
@@ -139,7 +255,7 @@ impl<'a> Iterator for Tokenizer<'a> {
                     }
                 }
 
-                let s = &start.input[start.offset..self.offset];
+                let s = &self.input[start_offset..self.offset];
 
                 Some((match s {
                     "current" => Token::Current,
@@ -149,7 +265,10 @@ impl<'a> Iterator for Tokenizer<'a> {
                     _ => Token::Ident
                 }, SmolStr::new(s)))
             },
-            _ => Some((Token::Error, SmolStr::new(&start.input[start.offset..self.offset])))
+            _ => {
+                self.errors.push(LexError::Unexpected(start_offset, c));
+                Some((Token::Error, SmolStr::new(&self.input[start_offset..self.offset])))
+            }
         }
     }
 }
@@ -308,4 +427,34 @@ mod tests {
             ]
         );
     }
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            tokenize(r"'\n' '\t' '\r' '\0' '\\' '\''"),
+            vec![
+                (Token::Char, r"'\n'".into()),
+                (Token::Whitespace, " ".into()),
+                (Token::Char, r"'\t'".into()),
+                (Token::Whitespace, " ".into()),
+                (Token::Char, r"'\r'".into()),
+                (Token::Whitespace, " ".into()),
+                (Token::Char, r"'\0'".into()),
+                (Token::Whitespace, " ".into()),
+                (Token::Char, r"'\\'".into()),
+                (Token::Whitespace, " ".into()),
+                (Token::Char, r"'\''".into())
+            ]
+        );
+        assert_eq!(
+            tokenize(r"'\x41' '\u{1}'"),
+            vec![
+                (Token::Char, r"'\x41'".into()),
+                (Token::Whitespace, " ".into()),
+                (Token::Char, r"'\u{1}'".into())
+            ]
+        );
+        let mut t = Tokenizer::new(r"'\z'");
+        assert_eq!(Iterator::next(&mut t), Some((Token::Error, r"'\z'".into())));
+        assert_eq!(t.errors(), &[LexError::InvalidEscape(0, 'z')]);
+    }
 }
@@ -65,6 +65,10 @@ impl_types! {
         pub fn generics(&self) -> impl Iterator<Item = Type<R>> {
             self.node().children().filter_map(Type::cast)
         }
+        /// Alias for `generics`.
+        pub fn generic_args(&self) -> impl Iterator<Item = Type<R>> {
+            self.generics()
+        }
     },
     Char (Token::Char) {
         pub fn value(&self) -> Option<u8> {
@@ -74,10 +78,44 @@ impl_types! {
             if c >= '0' && c <= '9' {
                 Some(c as u8)
             } else if c == '\'' {
-                let c = s.next().unwrap();
-                assert_eq!(s.next(), Some('\''));
-                assert!(c.is_ascii());
-                Some(c as u8)
+                let value = match s.next().unwrap() {
+                    '\\' => {
+                        let value = match s.next().unwrap() {
+                            'n' => b'\n',
+                            't' => b'\t',
+                            'r' => b'\r',
+                            '0' => 0,
+                            '\\' => b'\\',
+                            '\'' => b'\'',
+                            'x' => {
+                                let hi = s.next().unwrap().to_digit(16).unwrap();
+                                let lo = s.next().unwrap().to_digit(16).unwrap();
+                                ((hi << 4) | lo) as u8
+                            },
+                            'u' => {
+                                assert_eq!(s.next(), Some('{'));
+                                let mut value: u32 = 0;
+                                loop {
+                                    match s.next().unwrap() {
+                                        '}' => break,
+                                        c => value = value * 16 + c.to_digit(16).unwrap()
+                                    }
+                                }
+                                assert!(value <= 0xFF);
+                                value as u8
+                            },
+                            _ => panic!("invalid ast")
+                        };
+                        assert_eq!(s.next(), Some('\''));
+                        value
+                    },
+                    c => {
+                        assert_eq!(s.next(), Some('\''));
+                        assert!(c.is_ascii());
+                        c as u8
+                    }
+                };
+                Some(value)
             } else if c == '_' {
                 None
             } else {
@@ -94,6 +132,10 @@ impl_types! {
                 _ => panic!("invalid ast")
             }
         }
+        /// Alias for `operation`.
+        pub fn direction(&self) -> Move {
+            self.operation()
+        }
     },
     SetStart (Token::SetStart) {
         pub fn target(&self) -> Type<R> {
@@ -116,6 +158,22 @@ impl_types! {
         pub fn do_move(&self) -> Movement<R> {
             nth!(self; (Movement) 0)
         }
+        /// Alias for `match_state`.
+        pub fn current_state(&self) -> Type<R> {
+            self.match_state()
+        }
+        /// Alias for `do_state`.
+        pub fn next_state(&self) -> Type<R> {
+            self.do_state()
+        }
+        /// The tape symbol this rule matches, as a `char`, or `None` for blank.
+        pub fn read_char(&self) -> Option<char> {
+            self.match_input().value().map(|b| b as char)
+        }
+        /// The tape symbol this rule writes, as a `char`, or `None` for blank.
+        pub fn write_char(&self) -> Option<char> {
+            self.do_write().value().map(|b| b as char)
+        }
     },
     Root (Token::Root) {
         pub fn start_assignments(&self) -> impl Iterator<Item = SetStart<R>> {
@@ -124,5 +182,9 @@ impl_types! {
         pub fn functions(&self) -> impl Iterator<Item = Function<R>> {
             self.node().children().filter_map(Function::cast)
         }
+        /// The state named by this program's (sole) `start` assignment, if any.
+        pub fn start_state(&self) -> Option<Type<R>> {
+            self.start_assignments().next().map(|s| s.target())
+        }
     }
 }